@@ -1,3 +1,5 @@
+use crate::vault::{self, EncryptedTokenStorage};
+#[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 use yup_oauth2::authenticator::Authenticator;
@@ -6,83 +8,140 @@ use yup_oauth2::{InstalledFlowAuthenticator, InstalledFlowReturnMethod};
 type BoxError = Box<dyn std::error::Error>;
 type HttpsConnector = hyper_rustls::HttpsConnector<hyper_util::client::legacy::connect::HttpConnector>;
 
-/// Set file permissions to owner-only read/write (0600).
+/// Best-effort owner-only permissions for the files this module still writes in plaintext
+/// (subscription name, unit preference). A no-op on non-Unix platforms; the sensitive
+/// credentials (tokens, client secret, project ID) are sealed in the vault regardless.
+#[cfg(unix)]
 fn restrict_permissions(path: &Path) -> Result<(), BoxError> {
     std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
     Ok(())
 }
 
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path) -> Result<(), BoxError> {
+    Ok(())
+}
+
 fn config_dir() -> Result<PathBuf, BoxError> {
     let dir = dirs::config_dir()
         .ok_or("Could not determine config directory")?
         .join("nest-cli");
     std::fs::create_dir_all(&dir)?;
-    // Restrict config directory to owner-only access
+    #[cfg(unix)]
     std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))?;
     Ok(dir)
 }
 
 fn token_path() -> Result<PathBuf, BoxError> {
-    Ok(config_dir()?.join("tokens.json"))
+    Ok(config_dir()?.join("tokens.json.enc"))
 }
 
 fn client_secret_path() -> Result<PathBuf, BoxError> {
-    Ok(config_dir()?.join("client_secret.json"))
+    Ok(config_dir()?.join("client_secret.json.enc"))
 }
 
 fn project_id_path() -> Result<PathBuf, BoxError> {
-    Ok(config_dir()?.join("project_id"))
+    Ok(config_dir()?.join("project_id.enc"))
 }
 
-/// Run the OAuth2 installed-app login flow and persist tokens.
-pub async fn login(client_secret_file: &str, project_id: &str) -> Result<(), BoxError> {
-    let secret = yup_oauth2::read_application_secret(client_secret_file).await?;
+fn subscription_path() -> Result<PathBuf, BoxError> {
+    Ok(config_dir()?.join("subscription"))
+}
+
+fn unit_path() -> Result<PathBuf, BoxError> {
+    Ok(config_dir()?.join("unit"))
+}
 
-    // Copy the client secret to config dir for later use
-    let secret_dest = client_secret_path()?;
-    std::fs::copy(client_secret_file, &secret_dest)?;
-    restrict_permissions(&secret_dest)?;
+/// Run the OAuth2 installed-app login flow and persist tokens and client secret, both
+/// sealed in the encrypted vault (see [`crate::vault`]).
+pub async fn login(client_secret_file: &str, project_id: &str, subscription: &str) -> Result<(), BoxError> {
+    let passphrase = vault::read_passphrase()?;
 
-    // Save the project ID
-    let pid_path = project_id_path()?;
-    std::fs::write(&pid_path, project_id)?;
-    restrict_permissions(&pid_path)?;
+    let secret_bytes = std::fs::read(client_secret_file)?;
+    let secret = yup_oauth2::parse_application_secret(&secret_bytes)?;
+    vault::write_sealed(&client_secret_path()?, &passphrase, &secret_bytes)?;
+
+    // Save the project ID, sealed in the vault alongside the client secret
+    vault::write_sealed(&project_id_path()?, &passphrase, project_id.as_bytes())?;
+
+    // Save the Pub/Sub subscription name used by `watch`
+    let sub_path = subscription_path()?;
+    std::fs::write(&sub_path, subscription)?;
+    restrict_permissions(&sub_path)?;
+
+    let storage = EncryptedTokenStorage::load(token_path()?, passphrase)?;
 
     let auth = InstalledFlowAuthenticator::builder(secret, InstalledFlowReturnMethod::HTTPRedirect)
-        .persist_tokens_to_disk(token_path()?)
+        .with_storage(Box::new(storage))
         .build()
         .await?;
 
-    // Request the SDM scope to trigger the browser-based OAuth flow
-    let scopes = &["https://www.googleapis.com/auth/sdm.service"];
+    // Request the SDM and Pub/Sub scopes to trigger the browser-based OAuth flow
+    let scopes = &[
+        "https://www.googleapis.com/auth/sdm.service",
+        "https://www.googleapis.com/auth/pubsub",
+    ];
     auth.token(scopes).await?;
 
-    println!("Login successful! Tokens saved.");
+    println!("Login successful! Tokens saved (encrypted).");
     Ok(())
 }
 
-/// Build an authenticator from previously saved credentials.
-pub async fn get_authenticator() -> Result<Authenticator<HttpsConnector>, BoxError> {
+/// Build an authenticator from previously saved, vault-encrypted credentials. Takes an
+/// already-read `passphrase` so callers that also need [`get_project_id`] only prompt once.
+pub async fn get_authenticator(passphrase: &str) -> Result<Authenticator<HttpsConnector>, BoxError> {
     let secret_path = client_secret_path()?;
     if !secret_path.exists() {
         return Err("Not logged in. Run `nest-cli auth login` first.".into());
     }
 
-    let secret = yup_oauth2::read_application_secret(&secret_path).await?;
+    let secret_bytes = vault::read_sealed(&secret_path, passphrase)?;
+    let secret = yup_oauth2::parse_application_secret(&secret_bytes)?;
+
+    let storage = EncryptedTokenStorage::load(token_path()?, passphrase.to_string())?;
 
     let auth = InstalledFlowAuthenticator::builder(secret, InstalledFlowReturnMethod::HTTPRedirect)
-        .persist_tokens_to_disk(token_path()?)
+        .with_storage(Box::new(storage))
         .build()
         .await?;
 
     Ok(auth)
 }
 
-/// Read the saved SDM project ID.
-pub fn get_project_id() -> Result<String, BoxError> {
+/// Read the saved SDM project ID, sealed in the vault, under an already-read `passphrase`.
+pub fn get_project_id(passphrase: &str) -> Result<String, BoxError> {
     let path = project_id_path()?;
     if !path.exists() {
         return Err("No project ID saved. Run `nest-cli auth login` first.".into());
     }
+    let bytes = vault::read_sealed(&path, passphrase)?;
+    Ok(String::from_utf8(bytes)?.trim().to_string())
+}
+
+/// Read the saved Pub/Sub subscription name used by `watch`.
+pub fn get_subscription_id() -> Result<String, BoxError> {
+    let path = subscription_path()?;
+    if !path.exists() {
+        return Err(
+            "No Pub/Sub subscription saved. Run `nest-cli auth login --subscription <name>` first.".into(),
+        );
+    }
     Ok(std::fs::read_to_string(path)?.trim().to_string())
 }
+
+/// Persist the user's preferred temperature unit so future commands default to it.
+pub fn save_unit(unit: &str) -> Result<(), BoxError> {
+    let path = unit_path()?;
+    std::fs::write(&path, unit)?;
+    restrict_permissions(&path)?;
+    Ok(())
+}
+
+/// Read the saved temperature unit preference, if any was set during login.
+pub fn get_saved_unit() -> Result<Option<String>, BoxError> {
+    let path = unit_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(std::fs::read_to_string(path)?.trim().to_string()))
+}
@@ -1,23 +1,40 @@
+use base64::Engine;
+use bytes::Bytes;
 use google_smartdevicemanagement1::api::{
     GoogleHomeEnterpriseSdmV1Device, GoogleHomeEnterpriseSdmV1ExecuteDeviceCommandRequest,
+    GoogleHomeEnterpriseSdmV1Structure,
 };
 use google_smartdevicemanagement1::SmartDeviceManagement;
-use serde_json::Value;
+use http_body_util::{BodyExt, Full};
+use hyper::{Method, Request};
+use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::time::Duration;
+use yup_oauth2::authenticator::Authenticator;
 
 type BoxError = Box<dyn std::error::Error>;
 type Connector = hyper_rustls::HttpsConnector<hyper_util::client::legacy::connect::HttpConnector>;
 type Hub = SmartDeviceManagement<Connector>;
+type RawHttpClient = hyper_util::client::legacy::Client<Connector, Full<Bytes>>;
+
+const PUBSUB_SCOPE: &str = "https://www.googleapis.com/auth/pubsub";
+/// How long to back off between Pub/Sub pulls that return no messages.
+const EMPTY_PULL_BACKOFF: Duration = Duration::from_secs(2);
 
 pub struct Client {
     hub: Hub,
+    http: RawHttpClient,
+    auth: Authenticator<Connector>,
     project_id: String,
 }
 
 impl Client {
     pub async fn new() -> Result<Self, BoxError> {
-        let auth = crate::auth::get_authenticator().await?;
-        let project_id = crate::auth::get_project_id()?;
+        // Read the vault passphrase once and thread it through, instead of letting
+        // `get_authenticator` and `get_project_id` each prompt for it independently.
+        let passphrase = crate::vault::read_passphrase()?;
+        let auth = crate::auth::get_authenticator(&passphrase).await?;
+        let project_id = crate::auth::get_project_id(&passphrase)?;
 
         let connector = hyper_rustls::HttpsConnectorBuilder::new()
             .with_native_roots()?
@@ -28,11 +45,21 @@ impl Client {
         let http_client = hyper_util::client::legacy::Client::builder(
             hyper_util::rt::TokioExecutor::new(),
         )
+        .build(connector.clone());
+
+        let raw_http = hyper_util::client::legacy::Client::builder(
+            hyper_util::rt::TokioExecutor::new(),
+        )
         .build(connector);
 
-        let hub = SmartDeviceManagement::new(http_client, auth);
+        let hub = SmartDeviceManagement::new(http_client, auth.clone());
 
-        Ok(Self { hub, project_id })
+        Ok(Self {
+            hub,
+            http: raw_http,
+            auth,
+            project_id,
+        })
     }
 
     fn parent(&self) -> String {
@@ -60,6 +87,11 @@ impl Client {
         Ok(device)
     }
 
+    pub async fn list_structures(&self) -> Result<Vec<GoogleHomeEnterpriseSdmV1Structure>, BoxError> {
+        let (_, response) = self.hub.enterprises().structures_list(&self.parent()).doit().await?;
+        Ok(response.structures.unwrap_or_default())
+    }
+
     pub async fn execute_command(
         &self,
         id: &str,
@@ -78,4 +110,121 @@ impl Client {
             .await?;
         Ok(())
     }
+
+    /// Pull events for `device_name` from `subscription` until interrupted, invoking
+    /// `on_update` with only the traits whose value differs from the last value seen for
+    /// that trait (every trait counts as changed the first time it is seen). The Pub/Sub
+    /// API has no push-to-async-stream primitive here, so this just loops `pull`/`acknowledge`
+    /// with a short backoff when a pull comes back empty.
+    pub async fn pull_events(
+        &self,
+        subscription: &str,
+        device_name: &str,
+        mut on_update: impl FnMut(&HashMap<String, Value>),
+    ) -> Result<(), BoxError> {
+        let mut last_traits: HashMap<String, Value> = HashMap::new();
+
+        loop {
+            let messages = self.pull_messages(subscription, 20).await?;
+            if messages.is_empty() {
+                tokio::time::sleep(EMPTY_PULL_BACKOFF).await;
+                continue;
+            }
+
+            let mut ack_ids = Vec::new();
+            for message in messages {
+                if let Some(ack_id) = message.get("ackId").and_then(|v| v.as_str()) {
+                    ack_ids.push(ack_id.to_string());
+                }
+
+                let Some(data_b64) = message
+                    .get("message")
+                    .and_then(|m| m.get("data"))
+                    .and_then(|v| v.as_str())
+                else {
+                    continue;
+                };
+
+                let Ok(decoded) = base64::engine::general_purpose::STANDARD.decode(data_b64) else {
+                    eprintln!("Skipping Pub/Sub message with malformed base64 data");
+                    continue;
+                };
+                let Ok(envelope) = serde_json::from_slice::<Value>(&decoded) else {
+                    eprintln!("Skipping Pub/Sub message with malformed JSON data");
+                    continue;
+                };
+
+                let resource_name = envelope
+                    .get("resourceUpdate")
+                    .and_then(|r| r.get("name"))
+                    .and_then(|v| v.as_str());
+                if resource_name != Some(device_name) {
+                    continue;
+                }
+
+                let Some(traits) = envelope
+                    .get("resourceUpdate")
+                    .and_then(|r| r.get("traits"))
+                    .and_then(|v| v.as_object())
+                else {
+                    continue;
+                };
+
+                let mut changed = HashMap::new();
+                for (name, value) in traits {
+                    if last_traits.get(name) != Some(value) {
+                        changed.insert(name.clone(), value.clone());
+                    }
+                    last_traits.insert(name.clone(), value.clone());
+                }
+
+                if !changed.is_empty() {
+                    on_update(&changed);
+                }
+            }
+
+            if !ack_ids.is_empty() {
+                self.acknowledge(subscription, &ack_ids).await?;
+            }
+        }
+    }
+
+    async fn pull_messages(&self, subscription: &str, max_messages: i64) -> Result<Vec<Value>, BoxError> {
+        let body = json!({ "maxMessages": max_messages });
+        let response = self.pubsub_post(&format!("{subscription}:pull"), body).await?;
+        Ok(response
+            .get("receivedMessages")
+            .and_then(|v| v.as_array())
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn acknowledge(&self, subscription: &str, ack_ids: &[String]) -> Result<(), BoxError> {
+        let body = json!({ "ackIds": ack_ids });
+        self.pubsub_post(&format!("{subscription}:acknowledge"), body).await?;
+        Ok(())
+    }
+
+    async fn pubsub_post(&self, path: &str, body: Value) -> Result<Value, BoxError> {
+        let token = self.auth.token(&[PUBSUB_SCOPE]).await?;
+        let access_token = token.token().ok_or("Authenticator returned no access token")?;
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(format!("https://pubsub.googleapis.com/v1/{path}"))
+            .header("Authorization", format!("Bearer {access_token}"))
+            .header("Content-Type", "application/json")
+            .body(Full::new(Bytes::from(serde_json::to_vec(&body)?)))?;
+
+        let response = self.http.request(request).await?;
+        let status = response.status();
+        let body_bytes = response.into_body().collect().await?.to_bytes();
+
+        if !status.is_success() {
+            let text = String::from_utf8_lossy(&body_bytes);
+            return Err(format!("Pub/Sub request to {path} failed ({status}): {text}").into());
+        }
+
+        Ok(serde_json::from_slice(&body_bytes)?)
+    }
 }
@@ -1,4 +1,6 @@
 use crate::client::Client;
+use google_smartdevicemanagement1::api::{GoogleHomeEnterpriseSdmV1Device, GoogleHomeEnterpriseSdmV1Structure};
+use serde::Serialize;
 use serde_json::{json, Value};
 use std::collections::HashMap;
 
@@ -6,6 +8,10 @@ type BoxError = Box<dyn std::error::Error>;
 
 const THERMOSTAT_TYPE: &str = "sdm.devices.types.THERMOSTAT";
 
+/// Minimum Celsius gap Nest enforces between heat and cool setpoints in HEATCOOL mode.
+/// The SDM API doesn't expose this per device, so we use Nest's documented minimum swing.
+const MIN_HEATCOOL_DEADBAND_C: f64 = 1.5;
+
 fn celsius_to_fahrenheit(c: f64) -> f64 {
     c * 9.0 / 5.0 + 32.0
 }
@@ -14,115 +20,423 @@ fn fahrenheit_to_celsius(f: f64) -> f64 {
     (f - 32.0) * 5.0 / 9.0
 }
 
+/// Convert a temperature *difference* (not an absolute reading) from Celsius to Fahrenheit.
+fn celsius_delta_to_fahrenheit(delta_c: f64) -> f64 {
+    delta_c * 9.0 / 5.0
+}
+
 fn get_trait<'a>(traits: &'a HashMap<String, Value>, name: &str) -> Option<&'a Value> {
     traits.get(&format!("sdm.devices.traits.{name}"))
 }
 
-pub async fn auth_login(client_secret: &str, project_id: &str) -> Result<(), BoxError> {
-    crate::auth::login(client_secret, project_id).await
+/// A device's owning structure ID and room (display name), in that order, from its
+/// `Where`/parent relation.
+fn device_location(device: &GoogleHomeEnterpriseSdmV1Device) -> (Option<String>, Option<String>) {
+    let relation = device.parent_relations.as_ref().and_then(|rels| rels.first());
+    let room_name = relation.and_then(|r| r.display_name.clone());
+    let structure_id = relation
+        .and_then(|r| r.parent.as_deref())
+        .and_then(|p| p.split("/structures/").nth(1))
+        .map(|rest| rest.split('/').next().unwrap_or(rest).to_string());
+    (structure_id, room_name)
+}
+
+fn structure_id(structure: &GoogleHomeEnterpriseSdmV1Structure) -> Option<String> {
+    structure.name.as_deref()?.rsplit('/').next().map(String::from)
+}
+
+fn structure_display_name(structure: &GoogleHomeEnterpriseSdmV1Structure) -> Option<String> {
+    structure
+        .traits
+        .as_ref()
+        .and_then(|t| t.get("sdm.structures.traits.Info"))
+        .and_then(|v| v.get("customName"))
+        .and_then(|v| v.as_str())
+        .map(String::from)
+}
+
+/// The user's preferred unit for temperature input/output, set via `--unit`, `NEST_CLI_UNIT`,
+/// or saved during `auth login`. The SDM API itself always speaks Celsius.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum TemperatureUnit {
+    Celsius,
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    pub fn as_config_str(&self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "celsius",
+            TemperatureUnit::Fahrenheit => "fahrenheit",
+        }
+    }
+
+    pub fn from_config_str(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "celsius" => Some(TemperatureUnit::Celsius),
+            "fahrenheit" => Some(TemperatureUnit::Fahrenheit),
+            _ => None,
+        }
+    }
 }
 
-pub async fn list_devices() -> Result<(), BoxError> {
+/// Format a Celsius reading in the user's preferred unit, with the other unit in parentheses.
+fn format_temp(unit: TemperatureUnit, celsius: f64) -> String {
+    match unit {
+        TemperatureUnit::Fahrenheit => format!("{:.1}°F ({:.1}°C)", celsius_to_fahrenheit(celsius), celsius),
+        TemperatureUnit::Celsius => format!("{:.1}°C ({:.1}°F)", celsius, celsius_to_fahrenheit(celsius)),
+    }
+}
+
+pub async fn auth_login(
+    client_secret: &str,
+    project_id: &str,
+    subscription: &str,
+    unit: Option<TemperatureUnit>,
+) -> Result<(), BoxError> {
+    if let Some(unit) = unit {
+        crate::auth::save_unit(unit.as_config_str())?;
+    }
+    crate::auth::login(client_secret, project_id, subscription).await
+}
+
+/// One entry of `devices list` output, shared by the human-readable and `--json` paths.
+#[derive(Serialize)]
+pub struct DeviceSummary {
+    pub id: String,
+    pub custom_name: String,
+    pub name: String,
+}
+
+fn extract_device_summary(device: &GoogleHomeEnterpriseSdmV1Device) -> DeviceSummary {
+    let name = device.name.clone().unwrap_or_else(|| "unknown".to_string());
+    let custom_name = device
+        .traits
+        .as_ref()
+        .and_then(|t| t.get("sdm.devices.traits.Info"))
+        .and_then(|v| v.get("customName"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("(unnamed)")
+        .to_string();
+    // Extract just the device ID portion for convenience
+    let id = name.rsplit('/').next().unwrap_or(&name).to_string();
+
+    DeviceSummary { id, custom_name, name }
+}
+
+pub async fn list_devices(json: bool) -> Result<(), BoxError> {
     let client = Client::new().await?;
     let devices = client.list_devices().await?;
 
-    let thermostats: Vec<_> = devices
+    let thermostats: Vec<DeviceSummary> = devices
         .iter()
         .filter(|d| d.type_.as_deref() == Some(THERMOSTAT_TYPE))
+        .map(extract_device_summary)
         .collect();
 
+    if json {
+        println!("{}", serde_json::to_string_pretty(&thermostats)?);
+        return Ok(());
+    }
+
     if thermostats.is_empty() {
         println!("No thermostats found.");
         return Ok(());
     }
 
-    for device in thermostats {
-        let name = device.name.as_deref().unwrap_or("unknown");
-        let custom_name = device
-            .traits
-            .as_ref()
-            .and_then(|t| t.get("sdm.devices.traits.Info"))
-            .and_then(|v| v.get("customName"))
-            .and_then(|v| v.as_str())
-            .unwrap_or("(unnamed)");
-
-        // Extract just the device ID portion for convenience
-        let short_id = name.rsplit('/').next().unwrap_or(name);
-        println!("{short_id}  {custom_name}");
+    for device in &thermostats {
+        println!("{}  {}", device.id, device.custom_name);
     }
 
     Ok(())
 }
 
-pub async fn device_status(id: &str) -> Result<(), BoxError> {
+/// `devices status` output, shared by the human-readable and `--json` paths so the two
+/// never drift: both are built from a single extraction step over the raw trait map.
+#[derive(Serialize)]
+pub struct DeviceStatus {
+    pub name: String,
+    pub room: Option<String>,
+    pub structure: Option<String>,
+    pub ambient_temperature_fahrenheit: Option<f64>,
+    pub ambient_temperature_celsius: Option<f64>,
+    pub humidity_percent: Option<f64>,
+    pub mode: Option<String>,
+    pub hvac_status: Option<String>,
+    pub heat_setpoint_fahrenheit: Option<f64>,
+    pub heat_setpoint_celsius: Option<f64>,
+    pub cool_setpoint_fahrenheit: Option<f64>,
+    pub cool_setpoint_celsius: Option<f64>,
+    pub eco: Option<String>,
+    pub connectivity: Option<String>,
+}
+
+fn extract_device_status(traits: &HashMap<String, Value>) -> DeviceStatus {
+    let name = get_trait(traits, "Info")
+        .and_then(|v| v.get("customName"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("(unnamed)")
+        .to_string();
+
+    let ambient_temperature_celsius = get_trait(traits, "Temperature")
+        .and_then(|v| v.get("ambientTemperatureCelsius"))
+        .and_then(|v| v.as_f64());
+
+    let humidity_percent = get_trait(traits, "Humidity")
+        .and_then(|v| v.get("ambientHumidityPercent"))
+        .and_then(|v| v.as_f64());
+
+    let mode = get_trait(traits, "ThermostatMode")
+        .and_then(|v| v.get("mode"))
+        .and_then(|v| v.as_str())
+        .map(String::from);
+
+    let hvac_status = get_trait(traits, "ThermostatHvac")
+        .and_then(|v| v.get("status"))
+        .and_then(|v| v.as_str())
+        .map(String::from);
+
+    let setpoint = get_trait(traits, "ThermostatTemperatureSetpoint");
+    let heat_setpoint_celsius = setpoint.and_then(|v| v.get("heatCelsius")).and_then(|v| v.as_f64());
+    let cool_setpoint_celsius = setpoint.and_then(|v| v.get("coolCelsius")).and_then(|v| v.as_f64());
+
+    let eco = get_trait(traits, "ThermostatEco")
+        .and_then(|v| v.get("mode"))
+        .and_then(|v| v.as_str())
+        .map(String::from);
+
+    let connectivity = get_trait(traits, "Connectivity")
+        .and_then(|v| v.get("status"))
+        .and_then(|v| v.as_str())
+        .map(String::from);
+
+    DeviceStatus {
+        name,
+        room: None,
+        structure: None,
+        ambient_temperature_fahrenheit: ambient_temperature_celsius.map(celsius_to_fahrenheit),
+        ambient_temperature_celsius,
+        humidity_percent,
+        mode,
+        hvac_status,
+        heat_setpoint_fahrenheit: heat_setpoint_celsius.map(celsius_to_fahrenheit),
+        heat_setpoint_celsius,
+        cool_setpoint_fahrenheit: cool_setpoint_celsius.map(celsius_to_fahrenheit),
+        cool_setpoint_celsius,
+        eco,
+        connectivity,
+    }
+}
+
+pub async fn device_status(id: &str, unit: TemperatureUnit, json: bool) -> Result<(), BoxError> {
     let client = Client::new().await?;
     let device = client.get_device(id).await?;
 
     let traits = device.traits.as_ref().ok_or("Device has no traits")?;
+    let mut status = extract_device_status(traits);
+
+    let (struct_id, room) = device_location(&device);
+    status.room = room;
+    status.structure = match &struct_id {
+        Some(sid) => client
+            .list_structures()
+            .await?
+            .iter()
+            .find(|s| structure_id(s).as_deref() == Some(sid.as_str()))
+            .and_then(structure_display_name),
+        None => None,
+    };
 
-    let custom_name = get_trait(traits, "Info")
-        .and_then(|v| v.get("customName"))
-        .and_then(|v| v.as_str())
-        .unwrap_or("(unnamed)");
+    if json {
+        println!("{}", serde_json::to_string_pretty(&status)?);
+        return Ok(());
+    }
 
-    println!("Name: {custom_name}");
+    println!("Name: {}", status.name);
 
-    if let Some(temp_c) = get_trait(traits, "Temperature")
-        .and_then(|v| v.get("ambientTemperatureCelsius"))
-        .and_then(|v| v.as_f64())
-    {
-        println!("Temperature: {:.1}°F ({:.1}°C)", celsius_to_fahrenheit(temp_c), temp_c);
+    if let Some(structure) = &status.structure {
+        println!("Structure: {structure}");
+    }
+    if let Some(room) = &status.room {
+        println!("Room: {room}");
     }
 
-    if let Some(humidity) = get_trait(traits, "Humidity")
-        .and_then(|v| v.get("ambientHumidityPercent"))
-        .and_then(|v| v.as_f64())
-    {
+    if let Some(temp_c) = status.ambient_temperature_celsius {
+        println!("Temperature: {}", format_temp(unit, temp_c));
+    }
+
+    if let Some(humidity) = status.humidity_percent {
         println!("Humidity: {humidity:.0}%");
     }
 
-    if let Some(mode) = get_trait(traits, "ThermostatMode")
-        .and_then(|v| v.get("mode"))
-        .and_then(|v| v.as_str())
-    {
+    if let Some(mode) = &status.mode {
         println!("Mode: {mode}");
     }
 
-    if let Some(hvac_status) = get_trait(traits, "ThermostatHvac")
-        .and_then(|v| v.get("status"))
-        .and_then(|v| v.as_str())
-    {
+    if let Some(hvac_status) = &status.hvac_status {
         println!("HVAC: {hvac_status}");
     }
 
-    // Show setpoints
-    if let Some(setpoint) = get_trait(traits, "ThermostatTemperatureSetpoint") {
-        if let Some(heat_c) = setpoint.get("heatCelsius").and_then(|v| v.as_f64()) {
-            println!("Heat setpoint: {:.1}°F ({:.1}°C)", celsius_to_fahrenheit(heat_c), heat_c);
-        }
-        if let Some(cool_c) = setpoint.get("coolCelsius").and_then(|v| v.as_f64()) {
-            println!("Cool setpoint: {:.1}°F ({:.1}°C)", celsius_to_fahrenheit(cool_c), cool_c);
-        }
+    if let Some(heat_c) = status.heat_setpoint_celsius {
+        println!("Heat setpoint: {}", format_temp(unit, heat_c));
+    }
+    if let Some(cool_c) = status.cool_setpoint_celsius {
+        println!("Cool setpoint: {}", format_temp(unit, cool_c));
     }
 
-    if let Some(eco) = get_trait(traits, "ThermostatEco")
-        .and_then(|v| v.get("mode"))
-        .and_then(|v| v.as_str())
-    {
+    if let Some(eco) = &status.eco {
         println!("Eco: {eco}");
     }
 
-    if let Some(connectivity) = get_trait(traits, "Connectivity")
-        .and_then(|v| v.get("status"))
-        .and_then(|v| v.as_str())
-    {
+    if let Some(connectivity) = &status.connectivity {
         println!("Connectivity: {connectivity}");
     }
 
     Ok(())
 }
 
-pub async fn set_temperature(id: &str, temp_f: f64) -> Result<(), BoxError> {
+/// Print a tree of every thermostat grouped by structure and room, from one structures
+/// list and one devices list request.
+pub async fn home_status(unit: TemperatureUnit) -> Result<(), BoxError> {
+    let client = Client::new().await?;
+    let structures = client.list_structures().await?;
+    let devices = client.list_devices().await?;
+
+    let structure_names: HashMap<String, String> = structures
+        .iter()
+        .filter_map(|s| {
+            let id = structure_id(s)?;
+            let name = structure_display_name(s).unwrap_or_else(|| id.clone());
+            Some((id, name))
+        })
+        .collect();
+
+    let thermostats: Vec<&GoogleHomeEnterpriseSdmV1Device> = devices
+        .iter()
+        .filter(|d| d.type_.as_deref() == Some(THERMOSTAT_TYPE))
+        .collect();
+
+    if thermostats.is_empty() {
+        println!("No thermostats found.");
+        return Ok(());
+    }
+
+    let mut grouped: HashMap<String, HashMap<String, Vec<&GoogleHomeEnterpriseSdmV1Device>>> = HashMap::new();
+    for device in &thermostats {
+        let (struct_id, room) = device_location(device);
+        let structure_key = struct_id.unwrap_or_else(|| "(unknown structure)".to_string());
+        let room_key = room.unwrap_or_else(|| "(unassigned room)".to_string());
+        grouped.entry(structure_key).or_default().entry(room_key).or_default().push(device);
+    }
+
+    let mut structure_keys: Vec<&String> = grouped.keys().collect();
+    structure_keys.sort();
+
+    for structure_key in structure_keys {
+        let display = structure_names.get(structure_key).unwrap_or(structure_key);
+        println!("{display}");
+
+        let rooms = &grouped[structure_key];
+        let mut room_keys: Vec<&String> = rooms.keys().collect();
+        room_keys.sort();
+
+        for room_key in room_keys {
+            println!("  {room_key}");
+
+            for device in &rooms[room_key] {
+                let Some(traits) = device.traits.as_ref() else { continue };
+                let status = extract_device_status(traits);
+
+                let temp = status
+                    .ambient_temperature_celsius
+                    .map(|c| format_temp(unit, c))
+                    .unwrap_or_else(|| "n/a".to_string());
+                let mode = status.mode.as_deref().unwrap_or("n/a");
+                let humidity = status.humidity_percent.map(|h| format!("{h:.0}%")).unwrap_or_else(|| "n/a".to_string());
+                let connectivity = status.connectivity.as_deref().unwrap_or("n/a");
+
+                println!(
+                    "    {}: {temp}, mode {mode}, humidity {humidity}, {connectivity}",
+                    status.name
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Format a single changed trait as the same human-readable lines `device_status` prints.
+fn format_trait_update(name: &str, value: &Value, unit: TemperatureUnit) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    match name {
+        "sdm.devices.traits.Temperature" => {
+            if let Some(temp_c) = value.get("ambientTemperatureCelsius").and_then(|v| v.as_f64()) {
+                lines.push(format!("Temperature: {}", format_temp(unit, temp_c)));
+            }
+        }
+        "sdm.devices.traits.Humidity" => {
+            if let Some(humidity) = value.get("ambientHumidityPercent").and_then(|v| v.as_f64()) {
+                lines.push(format!("Humidity: {humidity:.0}%"));
+            }
+        }
+        "sdm.devices.traits.ThermostatMode" => {
+            if let Some(mode) = value.get("mode").and_then(|v| v.as_str()) {
+                lines.push(format!("Mode: {mode}"));
+            }
+        }
+        "sdm.devices.traits.ThermostatHvac" => {
+            if let Some(status) = value.get("status").and_then(|v| v.as_str()) {
+                lines.push(format!("HVAC: {status}"));
+            }
+        }
+        "sdm.devices.traits.ThermostatTemperatureSetpoint" => {
+            if let Some(heat_c) = value.get("heatCelsius").and_then(|v| v.as_f64()) {
+                lines.push(format!("Heat setpoint: {}", format_temp(unit, heat_c)));
+            }
+            if let Some(cool_c) = value.get("coolCelsius").and_then(|v| v.as_f64()) {
+                lines.push(format!("Cool setpoint: {}", format_temp(unit, cool_c)));
+            }
+        }
+        "sdm.devices.traits.ThermostatEco" => {
+            if let Some(eco) = value.get("mode").and_then(|v| v.as_str()) {
+                lines.push(format!("Eco: {eco}"));
+            }
+        }
+        "sdm.devices.traits.Connectivity" => {
+            if let Some(connectivity) = value.get("status").and_then(|v| v.as_str()) {
+                lines.push(format!("Connectivity: {connectivity}"));
+            }
+        }
+        _ => {}
+    }
+
+    lines
+}
+
+/// Stream live trait changes for a thermostat via the Pub/Sub subscription saved at login.
+pub async fn watch(id: &str, unit: TemperatureUnit) -> Result<(), BoxError> {
+    let client = Client::new().await?;
+    let subscription = crate::auth::get_subscription_id()?;
+
+    let device = client.get_device(id).await?;
+    let device_name = device.name.ok_or("Device has no name")?;
+
+    println!("Watching {id} for live updates (Ctrl+C to stop)...");
+
+    client
+        .pull_events(&subscription, &device_name, |changed| {
+            for (name, value) in changed {
+                for line in format_trait_update(name, value, unit) {
+                    println!("{line}");
+                }
+            }
+        })
+        .await
+}
+
+pub async fn set_temperature(id: &str, temp: f64, unit: TemperatureUnit) -> Result<(), BoxError> {
     let client = Client::new().await?;
 
     // Determine current mode to pick the right command
@@ -134,30 +448,98 @@ pub async fn set_temperature(id: &str, temp_f: f64) -> Result<(), BoxError> {
         .and_then(|v| v.as_str())
         .unwrap_or("HEAT");
 
-    let temp_c = fahrenheit_to_celsius(temp_f);
+    let temp_c = match unit {
+        TemperatureUnit::Celsius => temp,
+        TemperatureUnit::Fahrenheit => fahrenheit_to_celsius(temp),
+    };
     let mut params = HashMap::new();
 
-    let command = match mode {
+    let (command, message) = match mode {
         "COOL" => {
             params.insert("coolCelsius".to_string(), json!(temp_c));
-            "sdm.devices.commands.ThermostatTemperatureSetpoint.SetCool"
+            (
+                "sdm.devices.commands.ThermostatTemperatureSetpoint.SetCool",
+                format!("Set temperature to {}", format_temp(unit, temp_c)),
+            )
         }
         "HEATCOOL" => {
-            return Err(
-                "In HEATCOOL mode, use separate heat/cool setpoints. \
-                 Switch to HEAT or COOL mode first, or set range via the Google Home app."
-                    .into(),
-            );
+            // No single setpoint applies in HEATCOOL, so move whichever bound is nearer to
+            // the requested temperature and leave the other one where it was.
+            let setpoint = get_trait(traits, "ThermostatTemperatureSetpoint");
+            let heat_c = setpoint.and_then(|v| v.get("heatCelsius")).and_then(|v| v.as_f64()).unwrap_or(temp_c);
+            let cool_c = setpoint.and_then(|v| v.get("coolCelsius")).and_then(|v| v.as_f64()).unwrap_or(temp_c);
+
+            let (new_heat, new_cool) = if (temp_c - heat_c).abs() <= (temp_c - cool_c).abs() {
+                (temp_c, cool_c)
+            } else {
+                (heat_c, temp_c)
+            };
+
+            if new_cool - new_heat < MIN_HEATCOOL_DEADBAND_C {
+                return Err(format!(
+                    "Adjusting the nearer setpoint would leave less than {:.1}°C ({:.1}°F) between \
+                     heat and cool. Use `set range` to change both setpoints at once.",
+                    MIN_HEATCOOL_DEADBAND_C,
+                    celsius_delta_to_fahrenheit(MIN_HEATCOOL_DEADBAND_C)
+                )
+                .into());
+            }
+
+            params.insert("heatCelsius".to_string(), json!(new_heat));
+            params.insert("coolCelsius".to_string(), json!(new_cool));
+            (
+                "sdm.devices.commands.ThermostatTemperatureSetpoint.SetRange",
+                format!(
+                    "Set heat setpoint to {}, cool setpoint to {}",
+                    format_temp(unit, new_heat),
+                    format_temp(unit, new_cool)
+                ),
+            )
         }
         _ => {
             // Default to SetHeat for HEAT mode (and as fallback)
             params.insert("heatCelsius".to_string(), json!(temp_c));
-            "sdm.devices.commands.ThermostatTemperatureSetpoint.SetHeat"
+            (
+                "sdm.devices.commands.ThermostatTemperatureSetpoint.SetHeat",
+                format!("Set temperature to {}", format_temp(unit, temp_c)),
+            )
         }
     };
 
     client.execute_command(id, command, params).await?;
-    println!("Set temperature to {temp_f:.0}°F ({temp_c:.1}°C)");
+    println!("{message}");
+    Ok(())
+}
+
+pub async fn set_range(id: &str, low: f64, high: f64, unit: TemperatureUnit) -> Result<(), BoxError> {
+    if low >= high {
+        return Err("Low setpoint must be less than high setpoint.".into());
+    }
+
+    let (heat_c, cool_c) = match unit {
+        TemperatureUnit::Celsius => (low, high),
+        TemperatureUnit::Fahrenheit => (fahrenheit_to_celsius(low), fahrenheit_to_celsius(high)),
+    };
+
+    if cool_c - heat_c < MIN_HEATCOOL_DEADBAND_C {
+        return Err(format!(
+            "Heat and cool setpoints must be at least {:.1}°C ({:.1}°F) apart.",
+            MIN_HEATCOOL_DEADBAND_C,
+            celsius_delta_to_fahrenheit(MIN_HEATCOOL_DEADBAND_C)
+        )
+        .into());
+    }
+
+    let client = Client::new().await?;
+    let mut params = HashMap::new();
+    params.insert("heatCelsius".to_string(), json!(heat_c));
+    params.insert("coolCelsius".to_string(), json!(cool_c));
+
+    client
+        .execute_command(id, "sdm.devices.commands.ThermostatTemperatureSetpoint.SetRange", params)
+        .await?;
+
+    println!("Set range to {} - {}", format_temp(unit, heat_c), format_temp(unit, cool_c));
     Ok(())
 }
 
@@ -1,14 +1,25 @@
 mod auth;
 mod client;
 mod commands;
+mod vault;
 
 use clap::{Parser, Subcommand};
+use commands::TemperatureUnit;
 
 #[derive(Parser)]
 #[command(name = "nest-cli", about = "Control Google Nest thermostats via the SDM API")]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Temperature unit for input/output. Falls back to NEST_CLI_UNIT, then the unit
+    /// saved during `auth login`, then Fahrenheit.
+    #[arg(long, global = true, env = "NEST_CLI_UNIT", value_enum)]
+    unit: Option<TemperatureUnit>,
+
+    /// Emit machine-readable JSON instead of formatted text (supported by `devices` commands)
+    #[arg(long, global = true)]
+    json: bool,
 }
 
 #[derive(Subcommand)]
@@ -28,6 +39,16 @@ enum Commands {
         #[command(subcommand)]
         action: SetAction,
     },
+    /// Stream live trait changes for a thermostat via Pub/Sub
+    Watch {
+        /// Device ID (or full device name)
+        id: String,
+    },
+    /// View the home as a whole (structures and rooms)
+    Home {
+        #[command(subcommand)]
+        action: HomeAction,
+    },
 }
 
 #[derive(Subcommand)]
@@ -41,6 +62,10 @@ enum AuthAction {
         /// SDM project ID (from Device Access console)
         #[arg(long)]
         project_id: String,
+
+        /// Pub/Sub subscription name used by `watch` (from Device Access registration)
+        #[arg(long)]
+        subscription: String,
     },
 }
 
@@ -55,14 +80,20 @@ enum DeviceAction {
     },
 }
 
+#[derive(Subcommand)]
+enum HomeAction {
+    /// Show every thermostat grouped by structure and room
+    Status,
+}
+
 #[derive(Subcommand)]
 enum SetAction {
-    /// Set target temperature (in Fahrenheit)
+    /// Set target temperature (in the configured unit, see --unit)
     Temp {
         /// Device ID (or full device name)
         id: String,
-        /// Target temperature in Fahrenheit
-        temp_f: f64,
+        /// Target temperature, in the configured unit
+        temp: f64,
     },
     /// Set thermostat mode (heat, cool, heatcool, or off)
     Mode {
@@ -71,26 +102,50 @@ enum SetAction {
         /// Mode: heat, cool, heatcool, or off
         mode: String,
     },
+    /// Set heat/cool setpoints for HEATCOOL mode (in the configured unit, see --unit)
+    Range {
+        /// Device ID (or full device name)
+        id: String,
+        /// Low (heat) setpoint, in the configured unit
+        low: f64,
+        /// High (cool) setpoint, in the configured unit
+        high: f64,
+    },
+}
+
+/// Resolve the effective temperature unit: explicit `--unit`/`NEST_CLI_UNIT` (already
+/// merged into `cli.unit` by clap), else the unit saved during `auth login`, else Fahrenheit.
+fn resolve_unit(explicit: Option<TemperatureUnit>) -> TemperatureUnit {
+    explicit
+        .or_else(|| auth::get_saved_unit().ok().flatten().and_then(|s| TemperatureUnit::from_config_str(&s)))
+        .unwrap_or(TemperatureUnit::Fahrenheit)
 }
 
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
+    let unit = resolve_unit(cli.unit);
 
     let result = match cli.command {
         Commands::Auth { action } => match action {
             AuthAction::Login {
                 client_secret,
                 project_id,
-            } => commands::auth_login(&client_secret, &project_id).await,
+                subscription,
+            } => commands::auth_login(&client_secret, &project_id, &subscription, cli.unit).await,
         },
         Commands::Devices { action } => match action {
-            DeviceAction::List => commands::list_devices().await,
-            DeviceAction::Status { id } => commands::device_status(&id).await,
+            DeviceAction::List => commands::list_devices(cli.json).await,
+            DeviceAction::Status { id } => commands::device_status(&id, unit, cli.json).await,
         },
         Commands::Set { action } => match action {
-            SetAction::Temp { id, temp_f } => commands::set_temperature(&id, temp_f).await,
+            SetAction::Temp { id, temp } => commands::set_temperature(&id, temp, unit).await,
             SetAction::Mode { id, mode } => commands::set_mode(&id, &mode).await,
+            SetAction::Range { id, low, high } => commands::set_range(&id, low, high, unit).await,
+        },
+        Commands::Watch { id } => commands::watch(&id, unit).await,
+        Commands::Home { action } => match action {
+            HomeAction::Status => commands::home_status(unit).await,
         },
     };
 
@@ -0,0 +1,183 @@
+//! Encrypted-at-rest storage for OAuth tokens and the client secret.
+//!
+//! Credentials are sealed with AES-256-GCM using a key derived from a user passphrase
+//! (via Argon2) instead of relying on filesystem permissions, which do nothing on shared
+//! backups, synced dotfiles, or non-Unix systems.
+
+use aes_gcm::aead::{Aead, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::Argon2;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use yup_oauth2::storage::{TokenInfo, TokenStorage};
+
+type BoxError = Box<dyn std::error::Error>;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// On-disk layout for an encrypted blob: a random salt and nonce alongside the ciphertext.
+#[derive(Serialize, Deserialize)]
+struct SealedBlob {
+    salt: Vec<u8>,
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+/// Read the vault passphrase from `NEST_CLI_PASSPHRASE`, or prompt for it interactively.
+pub fn read_passphrase() -> Result<String, BoxError> {
+    if let Ok(pass) = std::env::var("NEST_CLI_PASSPHRASE") {
+        return Ok(pass);
+    }
+    rpassword::prompt_password("Vault passphrase: ").map_err(Into::into)
+}
+
+/// Derive a 256-bit AES key from the passphrase and salt via Argon2.
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32], BoxError> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("key derivation failed: {e}"))?;
+    Ok(key)
+}
+
+fn seal(passphrase: &str, plaintext: &[u8]) -> Result<SealedBlob, BoxError> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let key = derive_key(passphrase, &salt)?;
+
+    let mut nonce_bytes = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    let nonce = Nonce::from_slice(&nonce_bytes);
+
+    let cipher = Aes256Gcm::new_from_slice(&key)?;
+    let ciphertext = cipher
+        .encrypt(nonce, plaintext)
+        .map_err(|e| format!("encryption failed: {e}"))?;
+
+    Ok(SealedBlob {
+        salt: salt.to_vec(),
+        nonce: nonce_bytes.to_vec(),
+        ciphertext,
+    })
+}
+
+fn unseal(passphrase: &str, blob: &SealedBlob) -> Result<Vec<u8>, BoxError> {
+    let key = derive_key(passphrase, &blob.salt)?;
+    let cipher = Aes256Gcm::new_from_slice(&key)?;
+    let nonce = Nonce::from_slice(&blob.nonce);
+    cipher
+        .decrypt(nonce, blob.ciphertext.as_slice())
+        .map_err(|_| "failed to decrypt stored credentials (wrong passphrase?)".into())
+}
+
+/// Encrypt `plaintext` under `passphrase` and write it to `path`.
+pub fn write_sealed(path: &Path, passphrase: &str, plaintext: &[u8]) -> Result<(), BoxError> {
+    let blob = seal(passphrase, plaintext)?;
+    std::fs::write(path, serde_json::to_vec(&blob)?)?;
+    Ok(())
+}
+
+/// Read and decrypt the blob at `path` under `passphrase`.
+pub fn read_sealed(path: &Path, passphrase: &str) -> Result<Vec<u8>, BoxError> {
+    let blob: SealedBlob = serde_json::from_slice(&std::fs::read(path)?)?;
+    unseal(passphrase, &blob)
+}
+
+/// Tokens are cached per distinct scope set (sorted and joined), matching how
+/// `yup_oauth2`'s own disk storage keys its cache — this process requests both the SDM
+/// scope (`Client::new`) and the Pub/Sub scope (`Client::pubsub_post`) independently, and
+/// they must never be confused for one another.
+fn scope_key(scopes: &[&str]) -> String {
+    let mut sorted: Vec<&str> = scopes.to_vec();
+    sorted.sort_unstable();
+    sorted.join(" ")
+}
+
+/// A `yup_oauth2::TokenStorage` that keeps the active tokens in memory, keyed by scope
+/// set, and seals them back to `path` on every refresh instead of yup_oauth2's default
+/// plaintext disk storage.
+pub struct EncryptedTokenStorage {
+    path: std::path::PathBuf,
+    passphrase: String,
+    cache: Mutex<HashMap<String, TokenInfo>>,
+}
+
+impl EncryptedTokenStorage {
+    /// Load previously sealed tokens (if any) and wrap them for in-memory reuse. Fails if
+    /// a token blob already exists but can't be decrypted or parsed, rather than silently
+    /// falling back to an empty cache and masking a wrong-passphrase or corrupted-file error.
+    pub fn load(path: std::path::PathBuf, passphrase: String) -> Result<Self, BoxError> {
+        let cache = if path.exists() {
+            let bytes = read_sealed(&path, &passphrase)?;
+            serde_json::from_slice(&bytes)?
+        } else {
+            HashMap::new()
+        };
+        Ok(Self {
+            path,
+            passphrase,
+            cache: Mutex::new(cache),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl TokenStorage for EncryptedTokenStorage {
+    async fn set(&self, scopes: &[&str], token: TokenInfo) -> anyhow::Result<()> {
+        let bytes = {
+            let mut cache = self.cache.lock().unwrap();
+            cache.insert(scope_key(scopes), token);
+            serde_json::to_vec(&*cache)?
+        };
+        write_sealed(&self.path, &self.passphrase, &bytes).map_err(|e| anyhow::anyhow!(e))?;
+        Ok(())
+    }
+
+    async fn get(&self, scopes: &[&str]) -> Option<TokenInfo> {
+        self.cache.lock().unwrap().get(&scope_key(scopes)).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seal_unseal_roundtrip_recovers_plaintext() {
+        let blob = seal("correct horse", b"super secret tokens").unwrap();
+        let recovered = unseal("correct horse", &blob).unwrap();
+        assert_eq!(recovered, b"super secret tokens");
+    }
+
+    #[test]
+    fn unseal_with_wrong_passphrase_fails() {
+        let blob = seal("correct horse", b"super secret tokens").unwrap();
+        assert!(unseal("battery staple", &blob).is_err());
+    }
+
+    #[test]
+    fn seal_uses_a_fresh_nonce_and_salt_each_time() {
+        let first = seal("passphrase", b"same plaintext").unwrap();
+        let second = seal("passphrase", b"same plaintext").unwrap();
+        assert_ne!(first.nonce, second.nonce);
+        assert_ne!(first.salt, second.salt);
+        assert_ne!(first.ciphertext, second.ciphertext);
+    }
+
+    #[test]
+    fn scope_key_is_order_independent() {
+        assert_eq!(
+            scope_key(&["pubsub", "sdm.service"]),
+            scope_key(&["sdm.service", "pubsub"]),
+        );
+    }
+
+    #[test]
+    fn scope_key_distinguishes_distinct_scope_sets() {
+        assert_ne!(scope_key(&["sdm.service"]), scope_key(&["pubsub"]));
+    }
+}